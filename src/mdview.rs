@@ -5,20 +5,34 @@ use axum::{
     routing::get,
     Router,
 };
-use clap::{Arg, Command};
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use clap::{Arg, ArgAction, Command};
 use futures_util::{SinkExt, StreamExt};
+use headless_chrome::{protocol::cdp::Page, Browser, LaunchOptions};
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use pulldown_cmark::{html, CowStr, Event as MarkdownEvent, LinkType, Options, Parser, Tag};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event as MarkdownEvent, LinkType, Options, Parser, Tag, TagEnd};
 use std::{
     collections::HashSet,
     fs,
     net::SocketAddr,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, OnceLock},
     time::Duration,
 };
+use syntect::{
+    html::highlighted_html_for_string,
+    parsing::SyntaxSet,
+    highlighting::ThemeSet,
+};
 use tokio::sync::broadcast;
-use tower_http::services::ServeDir;
+use tower_http::{
+    compression::{
+        predicate::{NotForContentType, Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    services::ServeDir,
+};
 
 #[derive(Clone, Debug)]
 struct AppState {
@@ -26,6 +40,14 @@ struct AppState {
     root_dir: PathBuf,
     reload_sender: broadcast::Sender<()>,
     refresh_interval: Option<u64>,
+    syntax_theme: String,
+    is_directory: bool,
+    page_theme: String,
+    custom_css_path: Option<PathBuf>,
+    // False for the one-shot server spun up by the headless-chrome export path,
+    // where there's no `/ws` route and an embedded reconnect script would just
+    // trigger a `location.reload()` loop racing the screenshot/PDF capture.
+    embed_script: bool,
 }
 
 #[tokio::main]
@@ -35,7 +57,7 @@ async fn main() {
         .about("A fast markdown viewer with live reload")
         .arg(
             Arg::new("file")
-                .help("The markdown file to display")
+                .help("The markdown file to display, or a directory to browse")
                 .required(true)
                 .index(1),
         )
@@ -61,8 +83,74 @@ async fn main() {
                 .value_name("BROWSER")
                 .default_value("default"),
         )
+        .arg(
+            Arg::new("code-theme")
+                .long("code-theme")
+                .help("Syntect theme for fenced code block highlighting (e.g. InspiredGitHub, base16-ocean.dark, Solarized (dark))")
+                .value_name("THEME")
+                .default_value("InspiredGitHub")
+                .global(true),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .help("Page color theme")
+                .value_name("THEME")
+                .value_parser(["light", "dark", "github", "auto"])
+                .default_value("github")
+                .global(true),
+        )
+        .arg(
+            Arg::new("css")
+                .long("css")
+                .help("Path to a custom stylesheet injected after the built-in theme rules; re-read on every request")
+                .value_name("PATH")
+                .global(true),
+        )
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("export")
+                .about("Render a markdown file to a static artifact and exit")
+                .arg(
+                    Arg::new("file")
+                        .help("The markdown file to export")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .help("Output format")
+                        .value_name("FORMAT")
+                        .value_parser(["html", "pdf", "png"])
+                        .default_value("html"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("Output file path")
+                        .value_name("PATH")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("inline-images")
+                        .long("inline-images")
+                        .help("Inline local images as data URIs (html format only)")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
         .get_matches();
 
+    if let Some(export_matches) = matches.subcommand_matches("export") {
+        if let Err(e) = run_export(export_matches).await {
+            eprintln!("Export failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let file_path = PathBuf::from(matches.get_one::<String>("file").unwrap());
 
     if !file_path.exists() {
@@ -70,6 +158,8 @@ async fn main() {
         std::process::exit(1);
     }
 
+    let is_directory = file_path.is_dir();
+
     let browser = matches.get_one::<String>("browser").unwrap().clone();
 
     let port: u16 = matches
@@ -81,28 +171,60 @@ async fn main() {
         .get_one::<String>("refresh")
         .and_then(|s| s.parse().ok());
 
+    let syntax_theme = matches.get_one::<String>("code-theme").unwrap().clone();
+    let page_theme = matches.get_one::<String>("theme").unwrap().clone();
+    let custom_css_path = matches.get_one::<String>("css").map(PathBuf::from);
+
     let (reload_sender, _) = broadcast::channel(16);
 
-    let root_dir = file_path.parent().unwrap_or(Path::new(".")).to_path_buf();
-    
+    let root_dir = if is_directory {
+        file_path.clone()
+    } else {
+        file_path.parent().unwrap_or(Path::new(".")).to_path_buf()
+    };
+
     let state = AppState {
         file_path: file_path.clone(),
         root_dir,
         reload_sender: reload_sender.clone(),
         refresh_interval,
+        syntax_theme,
+        is_directory,
+        page_theme,
+        custom_css_path,
+        embed_script: true,
     };
 
-    // Set up file watching
+    // Set up file watching: the whole tree when browsing a directory, just the
+    // one file (and its parent, for create/delete detection) otherwise.
     let watched_files = Arc::new(tokio::sync::Mutex::new(HashSet::new()));
     let reload_sender_clone = reload_sender.clone();
     let watched_files_clone = watched_files.clone();
+    let watch_path = file_path.clone();
 
     tokio::spawn(async move {
-        if let Err(e) = setup_file_watcher(file_path, reload_sender_clone, watched_files_clone).await {
+        let result = if is_directory {
+            setup_directory_watcher(watch_path, reload_sender_clone).await
+        } else {
+            setup_file_watcher(watch_path, reload_sender_clone, watched_files_clone).await
+        };
+        if let Err(e) = result {
             eprintln!("File watcher error: {}", e);
         }
     });
 
+    // Compress compressible responses (html/css/js/json/svg/markdown) above a small
+    // size threshold; leave already-compressed binary types (images, pdf, video) alone.
+    let compression = CompressionLayer::new().compress_when(
+        SizeAbove::new(256)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::new("application/pdf"))
+            .and(NotForContentType::new("video/mp4"))
+            .and(NotForContentType::new("video/webm"))
+            .and(NotForContentType::new("audio/mpeg"))
+            .and(NotForContentType::new("audio/wav")),
+    );
+
     // Create router
     let app = Router::new()
         .route("/", get(serve_markdown))
@@ -110,6 +232,7 @@ async fn main() {
         .route("/md/*path", get(serve_linked_markdown))
         .route("/files/*path", get(serve_file))
         .nest_service("/static", ServeDir::new("static"))
+        .layer(compression)
         .with_state(state);
 
     // Start server
@@ -129,14 +252,301 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+async fn run_export(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = PathBuf::from(matches.get_one::<String>("file").unwrap());
+
+    if !file_path.exists() {
+        return Err(format!("File '{}' does not exist", file_path.display()).into());
+    }
+
+    let format = matches.get_one::<String>("format").unwrap().as_str();
+    let output_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let inline_images = matches.get_flag("inline-images");
+    let syntax_theme = matches.get_one::<String>("code-theme").unwrap().clone();
+    let page_theme = matches.get_one::<String>("theme").unwrap().clone();
+    let custom_css_path = matches.get_one::<String>("css").map(PathBuf::from);
+
+    match format {
+        "html" => export_html(&file_path, &output_path, inline_images, &syntax_theme, &page_theme, &custom_css_path),
+        "pdf" => export_via_headless_chrome(file_path, output_path, ExportKind::Pdf, syntax_theme, page_theme, custom_css_path).await,
+        "png" => export_via_headless_chrome(file_path, output_path, ExportKind::Png, syntax_theme, page_theme, custom_css_path).await,
+        other => Err(format!("Unsupported export format '{}'", other).into()),
+    }
+}
+
+fn export_html(
+    file_path: &Path,
+    output_path: &Path,
+    inline_images: bool,
+    syntax_theme: &str,
+    page_theme: &str,
+    custom_css_path: &Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(file_path)?;
+    let custom_css = read_custom_css(custom_css_path);
+    let mut html_content = markdown_to_html(&content, file_path, None, syntax_theme, false, page_theme, custom_css.as_deref());
+
+    if inline_images {
+        html_content = inline_local_images(&html_content, file_path);
+    }
+
+    fs::write(output_path, html_content)?;
+    println!("Wrote {}", output_path.display());
+    Ok(())
+}
+
+fn inline_local_images(html: &str, file_path: &Path) -> String {
+    // Only rewrite `<img src="/files/...">` — pulldown_cmark's HTML renderer
+    // always quotes attributes with `"`. A raw "/files/" substring scan would
+    // also catch `<a href="/files/...">` links to non-image files and any
+    // coincidental "/files/" text inside a highlighted code block.
+    let root_dir = file_path.parent().unwrap_or(Path::new("."));
+    let marker = "<img src=\"/files/";
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(marker) {
+        let (before, after) = rest.split_at(start);
+        result.push_str(before);
+        result.push_str("<img src=\"");
+
+        let after = &after[marker.len()..];
+        let end = after.find('"').unwrap_or(after.len());
+        let (relative_path, remainder) = after.split_at(end);
+
+        let data_uri = fs::read(root_dir.join(relative_path))
+            .ok()
+            .map(|bytes| format!("data:{};base64,{}", get_mime_type(&root_dir.join(relative_path)), BASE64_STANDARD.encode(bytes)));
+
+        match data_uri {
+            Some(uri) => result.push_str(&uri),
+            None => {
+                result.push_str("/files/");
+                result.push_str(relative_path);
+            }
+        }
+
+        rest = remainder;
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[derive(Clone, Copy)]
+enum ExportKind {
+    Pdf,
+    Png,
+}
+
+async fn export_via_headless_chrome(
+    file_path: PathBuf,
+    output_path: PathBuf,
+    kind: ExportKind,
+    syntax_theme: String,
+    page_theme: String,
+    custom_css_path: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root_dir = file_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let (reload_sender, _) = broadcast::channel(16);
+
+    let state = AppState {
+        file_path: file_path.clone(),
+        root_dir,
+        reload_sender,
+        refresh_interval: None,
+        syntax_theme,
+        is_directory: false,
+        page_theme,
+        custom_css_path,
+        embed_script: false,
+    };
+
+    let app = Router::new()
+        .route("/", get(serve_markdown))
+        .route("/md/*path", get(serve_linked_markdown))
+        .route("/files/*path", get(serve_file))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let url = format!("http://localhost:{}", addr.port());
+
+    let bytes = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let browser = Browser::new(LaunchOptions {
+            headless: true,
+            ..Default::default()
+        })?;
+        let tab = browser.new_tab()?;
+        tab.navigate_to(&url)?;
+        tab.wait_until_navigated()?;
+
+        match kind {
+            ExportKind::Pdf => Ok(tab.print_to_pdf(None)?),
+            ExportKind::Png => Ok(tab.capture_screenshot(
+                Page::CaptureScreenshotFormatOption::Png,
+                None,
+                None,
+                true,
+            )?),
+        }
+    })
+    .await??;
+
+    server.abort();
+    fs::write(&output_path, bytes)?;
+    println!("Wrote {}", output_path.display());
+
+    Ok(())
+}
+
 async fn serve_markdown(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let custom_css = read_custom_css(&state.custom_css_path);
+
+    if state.is_directory {
+        let body = render_directory_listing(&state.root_dir, &state.root_dir);
+        let page = render_page(
+            "Markdown Viewer",
+            &format!("<div class=\"dir-index\">{}</div>", body),
+            state.refresh_interval,
+            state.embed_script,
+            &state.page_theme,
+            custom_css.as_deref(),
+        );
+        return Ok(Html(page));
+    }
+
     let content = fs::read_to_string(&state.file_path)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let html_content = markdown_to_html(&content, &state.file_path, state.refresh_interval);
+    let html_content = markdown_to_html(
+        &content,
+        &state.file_path,
+        state.refresh_interval,
+        &state.syntax_theme,
+        state.embed_script,
+        &state.page_theme,
+        custom_css.as_deref(),
+    );
     Ok(Html(html_content))
 }
 
+fn read_custom_css(path: &Option<PathBuf>) -> Option<String> {
+    path.as_ref().and_then(|p| fs::read_to_string(p).ok())
+}
+
+// Controls, space and the characters listed below; '/' stays literal so nested
+// paths still read as a path, and other sub-delimiters such as `(`, `+`, `=`,
+// `@` are left as they are. This neutralizes `"`, `<`, `>`, `&`, `'` so the
+// encoded path is safe to interpolate directly into an HTML attribute.
+const PATH_SEGMENT_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'\\')
+    .add(b'^')
+    .add(b'|')
+    .add(b'\'')
+    .add(b'&')
+    .add(b';');
+
+fn encode_path_for_href(relative: &str) -> String {
+    utf8_percent_encode(relative, PATH_SEGMENT_ENCODE_SET).to_string()
+}
+
+fn render_directory_listing(root_dir: &Path, dir: &Path) -> String {
+    let mut visited = HashSet::new();
+    render_directory_listing_inner(root_dir, dir, &mut visited)
+}
+
+fn render_directory_listing_inner(root_dir: &Path, dir: &Path, visited: &mut HashSet<PathBuf>) -> String {
+    // Guard against symlink cycles: a directory we've already descended into
+    // (by canonical path) is skipped instead of recursed into again.
+    if let Ok(canonical) = dir.canonicalize() {
+        if !visited.insert(canonical) {
+            return String::new();
+        }
+    }
+
+    let mut entries: Vec<fs::DirEntry> = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir.filter_map(|entry| entry.ok()).collect(),
+        Err(_) => return String::new(),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut html = String::from("<ul>\n");
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if path.is_dir() {
+            let nested = render_directory_listing_inner(root_dir, &path, visited);
+            html.push_str(&format!(
+                "<li>{}\n{}</li>\n",
+                html_escape(&name),
+                nested
+            ));
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let href_path = encode_path_for_href(&relative);
+
+        let is_markdown = path
+            .extension()
+            .map_or(false, |ext| ext == "md" || ext == "markdown");
+
+        if is_markdown {
+            html.push_str(&format!(
+                r#"<li><a href="/md/{}">{}</a></li>"#,
+                href_path,
+                html_escape(&name)
+            ));
+        } else {
+            html.push_str(&format!(
+                r#"<li><span class="entry-kind">[{}]</span><a href="/files/{}">{}</a></li>"#,
+                file_category(&path),
+                href_path,
+                html_escape(&name)
+            ));
+        }
+        html.push('\n');
+    }
+
+    html.push_str("</ul>\n");
+    html
+}
+
+fn file_category(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .as_deref()
+    {
+        Some("doc") | Some("docx") | Some("odt") | Some("rtf") => "word",
+        Some("png") | Some("jpg") | Some("jpeg") | Some("gif") | Some("svg") | Some("webp") | Some("ico") | Some("bmp") => "image",
+        Some("pdf") => "pdf",
+        Some("rs") | Some("py") | Some("js") | Some("ts") | Some("go") | Some("c") | Some("cpp") | Some("h") | Some("java") | Some("rb") | Some("sh") => "code",
+        Some("zip") | Some("tar") | Some("gz") | Some("tgz") | Some("rar") | Some("7z") => "archive",
+        _ => "file",
+    }
+}
+
 async fn serve_linked_markdown(
     AxumPath(path): AxumPath<String>,
     State(state): State<AppState>,
@@ -161,8 +571,19 @@ async fn serve_linked_markdown(
     let content = fs::read_to_string(&file_path)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Render markdown without websocket/refresh functionality (only for main file)
-    let html_content = markdown_to_html(&content, &file_path, None);
+    // Refresh/poll-interval only applies to the main file, but the reconnect
+    // script still embeds (and is gated by state.embed_script like serve_markdown)
+    // so the export server's one-shot pages stay free of a failing /ws connection.
+    let custom_css = read_custom_css(&state.custom_css_path);
+    let html_content = markdown_to_html(
+        &content,
+        &file_path,
+        None,
+        &state.syntax_theme,
+        state.embed_script,
+        &state.page_theme,
+        custom_css.as_deref(),
+    );
     Ok(Html(html_content))
 }
 
@@ -237,12 +658,17 @@ async fn handle_websocket(socket: WebSocket, state: AppState) {
         }
     });
 
-    // Send reload notifications
+    // Send content-swap updates, falling back to a full reload if re-rendering fails
     let reload_sender = sender.clone();
     let reload_task = tokio::spawn(async move {
         while let Ok(_) = rx.recv().await {
+            let message = match render_content_fragment(&state) {
+                Some(fragment) => Message::Text(format!("html:{}", fragment)),
+                None => Message::Text("reload".to_string()),
+            };
+
             let mut sender = reload_sender.lock().await;
-            if sender.send(Message::Text("reload".to_string())).await.is_err() {
+            if sender.send(message).await.is_err() {
                 break;
             }
         }
@@ -306,20 +732,98 @@ async fn setup_file_watcher(
     Ok(())
 }
 
-fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u64>) -> String {
+async fn setup_directory_watcher(
+    root_dir: PathBuf,
+    reload_sender: broadcast::Sender<()>,
+) -> notify::Result<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut watcher = RecommendedWatcher::new(
+        move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event);
+            }
+        },
+        Config::default(),
+    )?;
+
+    watcher.watch(&root_dir, RecursiveMode::Recursive)?;
+
+    while let Some(event) = rx.recv().await {
+        let paths: Vec<PathBuf> = event.paths;
+        let should_reload = paths.iter().any(|path| {
+            path.is_dir() || path.extension().map_or(false, |ext| ext == "md" || ext == "markdown")
+        });
+
+        if should_reload {
+            let _ = reload_sender.send(());
+        }
+    }
+
+    Ok(())
+}
+
+fn markdown_to_html(
+    content: &str,
+    file_path: &Path,
+    refresh_interval: Option<u64>,
+    syntax_theme: &str,
+    embed_script: bool,
+    page_theme: &str,
+    custom_css: Option<&str>,
+) -> String {
+    let html_output = render_markdown_body(content, file_path, syntax_theme);
+    render_page("Markdown Viewer", &html_output, refresh_interval, embed_script, page_theme, custom_css)
+}
+
+fn render_content_fragment(state: &AppState) -> Option<String> {
+    if state.is_directory {
+        let listing = render_directory_listing(&state.root_dir, &state.root_dir);
+        Some(format!(r#"<div class="dir-index">{}</div>"#, listing))
+    } else {
+        let content = fs::read_to_string(&state.file_path).ok()?;
+        Some(render_markdown_body(&content, &state.file_path, &state.syntax_theme))
+    }
+}
+
+fn render_markdown_body(content: &str, file_path: &Path, syntax_theme: &str) -> String {
     let parser = Parser::new_ext(content, Options::all());
 
     let root_dir = file_path.parent().unwrap_or(Path::new("."));
-    
-    // Transform events to handle relative paths
-    let events: Vec<MarkdownEvent> = parser
-        .map(|event| transform_event(event, file_path, root_dir))
-        .collect();
+
+    // Transform events to handle relative paths, buffering fenced code blocks so
+    // they can be swapped for a single highlighted Html event once they close.
+    let mut events: Vec<MarkdownEvent> = Vec::new();
+    let mut code_block_lang: Option<String> = None;
+    let mut code_block_source = String::new();
+
+    for event in parser {
+        match event {
+            MarkdownEvent::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
+                code_block_lang = Some(lang.to_string());
+                code_block_source.clear();
+            }
+            MarkdownEvent::Text(text) if code_block_lang.is_some() => {
+                code_block_source.push_str(&text);
+            }
+            MarkdownEvent::End(TagEnd::CodeBlock) if code_block_lang.is_some() => {
+                let lang = code_block_lang.take().unwrap();
+                let highlighted = highlight_code_block(&code_block_source, &lang, syntax_theme);
+                events.push(MarkdownEvent::Html(CowStr::Boxed(highlighted.into_boxed_str())));
+            }
+            other => events.push(transform_event(other, file_path, root_dir)),
+        }
+    }
 
     let mut html_output = String::new();
     html::push_html(&mut html_output, events.into_iter());
+    html_output
+}
 
-    let websocket_script = if refresh_interval.is_some() {
+fn websocket_script(refresh_interval: Option<u64>, embed_script: bool) -> String {
+    if !embed_script {
+        String::new()
+    } else if refresh_interval.is_some() {
         format!(
             r#"
             <script>
@@ -337,6 +841,17 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
             ws.onmessage = function(event) {
                 if (event.data === 'reload') {
                     location.reload();
+                    return;
+                }
+                if (event.data.startsWith('html:')) {
+                    const scrollTop = document.documentElement.scrollTop;
+                    const content = document.getElementById('mdview-content');
+                    if (content) {
+                        content.innerHTML = event.data.slice('html:'.length);
+                        document.documentElement.scrollTop = scrollTop;
+                    } else {
+                        location.reload();
+                    }
                 }
             };
             ws.onclose = function() {
@@ -347,7 +862,22 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
             };
         </script>
         "#.to_string()
-    };
+    }
+}
+
+fn render_page(
+    title: &str,
+    body_html: &str,
+    refresh_interval: Option<u64>,
+    embed_script: bool,
+    page_theme: &str,
+    custom_css: Option<&str>,
+) -> String {
+    let script = websocket_script(refresh_interval, embed_script);
+    let theme_vars = theme_variables_css(page_theme);
+    let custom_css_block = custom_css
+        .map(|css| format!("\n    <style>\n{}\n    </style>", escape_style_close(css)))
+        .unwrap_or_default();
 
     format!(
         r#"<!DOCTYPE html>
@@ -355,8 +885,10 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Markdown Viewer</title>
+    <title>{}</title>
     <style>
+{}
+
         * {{
             box-sizing: border-box;
         }}
@@ -370,7 +902,8 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
         body {{
             font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
             line-height: 1.6;
-            color: #333;
+            background-color: var(--mdview-bg);
+            color: var(--mdview-fg);
             max-width: 800px;
             margin: 0 auto;
             padding: 20px;
@@ -382,11 +915,11 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
             font-weight: 600;
         }}
 
-        h1 {{ font-size: 2em; border-bottom: 1px solid #eee; padding-bottom: 0.3em; }}
-        h2 {{ font-size: 1.5em; border-bottom: 1px solid #eee; padding-bottom: 0.3em; }}
+        h1 {{ font-size: 2em; border-bottom: 1px solid var(--mdview-border); padding-bottom: 0.3em; }}
+        h2 {{ font-size: 1.5em; border-bottom: 1px solid var(--mdview-border); padding-bottom: 0.3em; }}
 
         code {{
-            background-color: #f6f8fa;
+            background-color: var(--mdview-code-bg);
             padding: 2px 4px;
             border-radius: 3px;
             font-family: 'SF Mono', Consolas, 'Liberation Mono', Menlo, monospace;
@@ -394,7 +927,7 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
         }}
 
         pre {{
-            background-color: #f6f8fa;
+            background-color: var(--mdview-code-bg);
             padding: 16px;
             border-radius: 6px;
             overflow-x: auto;
@@ -406,10 +939,10 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
         }}
 
         blockquote {{
-            border-left: 4px solid #dfe2e5;
+            border-left: 4px solid var(--mdview-border);
             padding-left: 16px;
             margin-left: 0;
-            color: #6a737d;
+            color: var(--mdview-muted);
         }}
 
         table {{
@@ -419,13 +952,13 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
         }}
 
         th, td {{
-            border: 1px solid #dfe2e5;
+            border: 1px solid var(--mdview-border);
             padding: 8px 12px;
             text-align: left;
         }}
 
         th {{
-            background-color: #f6f8fa;
+            background-color: var(--mdview-code-bg);
             font-weight: 600;
         }}
 
@@ -441,17 +974,112 @@ fn markdown_to_html(content: &str, file_path: &Path, refresh_interval: Option<u6
         .task-list-item input[type="checkbox"] {{
             margin-right: 0.5em;
         }}
-    </style>
+
+        .dir-index ul {{
+            list-style-type: none;
+            padding-left: 1.2em;
+        }}
+
+        .dir-index a {{
+            text-decoration: none;
+            color: var(--mdview-link);
+        }}
+
+        .dir-index a:hover {{
+            text-decoration: underline;
+        }}
+
+        .dir-index .entry-kind {{
+            color: var(--mdview-muted);
+            font-size: 0.85em;
+            margin-right: 0.4em;
+        }}
+    </style>{}
 </head>
 <body>
+<div id="mdview-content">
 {}
+</div>
 {}
 </body>
 </html>"#,
-        html_output, websocket_script
+        title, theme_vars, custom_css_block, body_html, script
     )
 }
 
+fn theme_color_vars(theme: &str) -> &'static str {
+    match theme {
+        "light" => "--mdview-bg: #ffffff; --mdview-fg: #24292e; --mdview-code-bg: #f5f5f5; --mdview-border: #dddddd; --mdview-link: #0366d6; --mdview-muted: #666666;",
+        "dark" => "--mdview-bg: #0d1117; --mdview-fg: #c9d1d9; --mdview-code-bg: #161b22; --mdview-border: #30363d; --mdview-link: #58a6ff; --mdview-muted: #8b949e;",
+        // "github" and any unrecognized value fall back to the original GitHub-ish palette.
+        _ => "--mdview-bg: #ffffff; --mdview-fg: #333333; --mdview-code-bg: #f6f8fa; --mdview-border: #dfe2e5; --mdview-link: #0969da; --mdview-muted: #6a737d;",
+    }
+}
+
+fn theme_variables_css(theme: &str) -> String {
+    if theme == "auto" {
+        format!(
+            ":root {{ {} }}\n        @media (prefers-color-scheme: dark) {{\n            :root {{ {} }}\n        }}",
+            theme_color_vars("light"),
+            theme_color_vars("dark")
+        )
+    } else {
+        format!(":root {{ {} }}", theme_color_vars(theme))
+    }
+}
+
+static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+
+fn highlight_code_block(source: &str, lang: &str, theme_name: &str) -> String {
+    let syntax_set = SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme_set = THEME_SET.get_or_init(ThemeSet::load_defaults);
+
+    let syntax = syntax_set
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+
+    let theme = theme_set
+        .themes
+        .get(theme_name)
+        .unwrap_or_else(|| &theme_set.themes["InspiredGitHub"]);
+
+    highlighted_html_for_string(source, syntax_set, syntax, theme)
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", html_escape(source)))
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_style_close(css: &str) -> String {
+    // custom_css is re-read from --css on every request, so it's untrusted the
+    // same way markdown content is. Break up any case-insensitive "</style"
+    // sequence with a zero-width space so it can't close the surrounding
+    // <style> element early and get parsed as page markup.
+    let chars: Vec<char> = css.chars().collect();
+    let needle: Vec<char> = "</style".chars().collect();
+    let mut result = String::with_capacity(css.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let is_match = i + needle.len() <= chars.len()
+            && chars[i..i + needle.len()]
+                .iter()
+                .zip(&needle)
+                .all(|(a, b)| a.eq_ignore_ascii_case(b));
+        if is_match {
+            result.push_str("</\u{200b}style");
+            i += needle.len();
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+    result
+}
+
 fn transform_event<'a>(event: MarkdownEvent<'a>, file_path: &Path, _root_dir: &Path) -> MarkdownEvent<'a> {
     match event {
         MarkdownEvent::Start(Tag::Image {